@@ -0,0 +1,80 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// A fixed-size Bloom filter used to skip disk lookups for keys that are
+/// definitely absent. False positives are possible (we might say "maybe
+/// present" for a key that was never stored) but false negatives are not:
+/// if any of a key's bits is clear, the key has never been inserted.
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    m: usize,
+    k: usize,
+}
+
+impl BloomFilter {
+    /// Sizes `m` (bit array length) and `k` (hash count) from the expected
+    /// number of elements and the desired false-positive rate using the
+    /// standard Bloom filter formulas.
+    pub fn new(expected_elements: usize, false_positive_rate: f64) -> Self {
+        let n = expected_elements.max(1) as f64;
+        let m = (-(n * false_positive_rate.ln()) / std::f64::consts::LN_2.powi(2)).ceil() as usize;
+        let m = m.max(8);
+        let k = ((m as f64 / n) * std::f64::consts::LN_2).round().max(1.0) as usize;
+
+        BloomFilter {
+            bits: vec![0u8; m.div_ceil(8)],
+            m,
+            k,
+        }
+    }
+
+    /// Derives the `k` bit indices for an item via double-hashing:
+    /// `h1 + i * h2 mod m`, seeded from two independent `DefaultHasher`s.
+    fn indices<T: Hash + ?Sized>(&self, item: &T) -> impl Iterator<Item = usize> + '_ {
+        let h1 = seeded_hash(item, 0);
+        let h2 = seeded_hash(item, 1);
+        let m = self.m as u64;
+
+        (0..self.k).map(move |i| (h1.wrapping_add((i as u64).wrapping_mul(h2)) % m) as usize)
+    }
+
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        for idx in self.indices(item).collect::<Vec<_>>() {
+            self.bits[idx / 8] |= 1 << (idx % 8);
+        }
+    }
+
+    pub fn might_contain<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.indices(item)
+            .all(|idx| self.bits[idx / 8] & (1 << (idx % 8)) != 0)
+    }
+}
+
+fn seeded_hash<T: Hash + ?Sized>(item: &T, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inserted_keys_are_found() {
+        let mut filter = BloomFilter::new(1000, 0.01);
+        filter.insert("hello");
+        filter.insert("world");
+
+        assert!(filter.might_contain("hello"));
+        assert!(filter.might_contain("world"));
+    }
+
+    #[test]
+    fn test_absent_key_can_be_rejected() {
+        let mut filter = BloomFilter::new(1000, 0.01);
+        filter.insert("hello");
+
+        assert!(!filter.might_contain("definitely-not-inserted"));
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+struct Node {
+    key: String,
+    value: String,
+    last_access: Instant,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// A bounded, byte-capacity, intrusive-LRU cache sitting in front of the
+/// disk layer. Nodes live in a slab (`nodes`); `prev`/`next` thread them
+/// into a doubly linked list so the least-recently-used entry (the tail)
+/// can be evicted in O(1) once `capacity_bytes` is exceeded.
+pub struct LruCache {
+    capacity_bytes: usize,
+    ttl: Option<Duration>,
+    nodes: Vec<Option<Node>>,
+    key_to_index: HashMap<String, usize>,
+    free_slots: Vec<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    used_bytes: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl LruCache {
+    /// `ttl`: entries older than this are treated as absent (and dropped)
+    /// instead of being returned. `None` means entries never expire.
+    pub fn new(capacity_bytes: usize, ttl: Option<Duration>) -> Self {
+        LruCache {
+            capacity_bytes,
+            ttl,
+            nodes: Vec::new(),
+            key_to_index: HashMap::new(),
+            free_slots: Vec::new(),
+            head: None,
+            tail: None,
+            used_bytes: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<String> {
+        let Some(&idx) = self.key_to_index.get(key) else {
+            self.misses += 1;
+            return None;
+        };
+
+        if let Some(ttl) = self.ttl {
+            if self.nodes[idx].as_ref().unwrap().last_access.elapsed() > ttl {
+                self.remove(idx);
+                self.misses += 1;
+                return None;
+            }
+        }
+
+        self.move_to_front(idx);
+        self.hits += 1;
+        let node = self.nodes[idx].as_mut().unwrap();
+        node.last_access = Instant::now();
+        Some(node.value.clone())
+    }
+
+    /// Inserts `val` for `key`, or updates it in place if the key is
+    /// already cached. Updating in place (rather than removing and
+    /// re-inserting) matters because eviction drops the *backing* entry
+    /// for whichever key is least recently used; if `store` evicted-then-
+    /// reinserted, a concurrent eviction could delete the entry for a key
+    /// that in fact still exists under its new value.
+    pub fn put(&mut self, key: &str, val: &str) {
+        if let Some(&idx) = self.key_to_index.get(key) {
+            let node = self.nodes[idx].as_mut().unwrap();
+            self.used_bytes = self.used_bytes - node.value.len() + val.len();
+            node.value = val.to_string();
+            node.last_access = Instant::now();
+            self.move_to_front(idx);
+        } else {
+            let idx = self.alloc_node(Node {
+                key: key.to_string(),
+                value: val.to_string(),
+                last_access: Instant::now(),
+                prev: None,
+                next: None,
+            });
+            self.key_to_index.insert(key.to_string(), idx);
+            self.used_bytes += val.len();
+            self.push_front(idx);
+        }
+
+        while self.used_bytes > self.capacity_bytes {
+            let Some(lru_idx) = self.tail else { break };
+            self.remove(lru_idx);
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    fn alloc_node(&mut self, node: Node) -> usize {
+        if let Some(idx) = self.free_slots.pop() {
+            self.nodes[idx] = Some(node);
+            idx
+        } else {
+            self.nodes.push(Some(node));
+            self.nodes.len() - 1
+        }
+    }
+
+    fn push_front(&mut self, idx: usize) {
+        let old_head = self.head;
+        {
+            let node = self.nodes[idx].as_mut().unwrap();
+            node.prev = None;
+            node.next = old_head;
+        }
+        if let Some(old_head) = old_head {
+            self.nodes[old_head].as_mut().unwrap().prev = Some(idx);
+        }
+        self.head = Some(idx);
+        if self.tail.is_none() {
+            self.tail = Some(idx);
+        }
+    }
+
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = {
+            let node = self.nodes[idx].as_ref().unwrap();
+            (node.prev, node.next)
+        };
+
+        match prev {
+            Some(prev) => self.nodes[prev].as_mut().unwrap().next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.nodes[next].as_mut().unwrap().prev = prev,
+            None => self.tail = prev,
+        }
+    }
+
+    fn move_to_front(&mut self, idx: usize) {
+        if self.head == Some(idx) {
+            return;
+        }
+        self.unlink(idx);
+        self.push_front(idx);
+    }
+
+    /// Drops the backing entry for the node at `idx`, whether it's being
+    /// evicted for capacity or expired via TTL.
+    fn remove(&mut self, idx: usize) {
+        self.unlink(idx);
+        let node = self.nodes[idx].take().unwrap();
+        self.key_to_index.remove(&node.key);
+        self.used_bytes -= node.value.len();
+        self.free_slots.push(idx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    #[test]
+    fn test_put_then_get_hits() {
+        let mut cache = LruCache::new(1024, None);
+        cache.put("a", "1");
+
+        assert_eq!(cache.get("a"), Some("1".to_string()));
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 0);
+    }
+
+    #[test]
+    fn test_miss_is_counted() {
+        let mut cache = LruCache::new(1024, None);
+        assert_eq!(cache.get("missing"), None);
+        assert_eq!(cache.misses(), 1);
+    }
+
+    #[test]
+    fn test_capacity_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2, None);
+        cache.put("a", "1"); // 1 byte
+        cache.put("b", "1"); // 2 bytes, at capacity
+        cache.put("c", "1"); // evicts "a" (least recently used)
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some("1".to_string()));
+        assert_eq!(cache.get("c"), Some("1".to_string()));
+    }
+
+    #[test]
+    fn test_update_in_place_keeps_entry_alive() {
+        let mut cache = LruCache::new(2, None);
+        cache.put("a", "1");
+        cache.put("b", "1");
+
+        // Updating "a" in place must not evict it to make room for itself.
+        cache.put("a", "1");
+
+        assert_eq!(cache.get("a"), Some("1".to_string()));
+        assert_eq!(cache.get("b"), Some("1".to_string()));
+    }
+
+    #[test]
+    fn test_expired_entry_is_treated_as_absent() {
+        let mut cache = LruCache::new(1024, Some(Duration::from_millis(1)));
+        cache.put("a", "1");
+        sleep(Duration::from_millis(10));
+
+        assert_eq!(cache.get("a"), None);
+    }
+}
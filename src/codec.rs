@@ -0,0 +1,83 @@
+use std::io::{self, Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+/// Value compression applied in `store` and reversed in `retrive`. Each
+/// record keeps its own one-byte tag (see [`Codec::tag`]) instead of a
+/// single codec being assumed for the whole store, so the configured
+/// codec can change over time without rewriting existing records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    Deflate,
+}
+
+impl Codec {
+    pub fn tag(self) -> u8 {
+        match self {
+            Codec::None => 0,
+            Codec::Deflate => 1,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> io::Result<Self> {
+        match tag {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Deflate),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown codec tag {other}"),
+            )),
+        }
+    }
+
+    pub fn encode(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(data.to_vec()),
+            Codec::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(data.to_vec()),
+            Codec::Deflate => {
+                let mut decoder = DeflateDecoder::new(data);
+                let mut out = Vec::new();
+                decoder.read_to_end(&mut out)?;
+                Ok(out)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_none_round_trips_unchanged() {
+        let data = b"hello world";
+        let encoded = Codec::None.encode(data).unwrap();
+        assert_eq!(encoded, data);
+        assert_eq!(Codec::None.decode(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn test_deflate_round_trips() {
+        let data = b"hello world, hello world, hello world";
+        let encoded = Codec::Deflate.encode(data).unwrap();
+        assert_eq!(Codec::Deflate.decode(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn test_unknown_tag_is_rejected() {
+        assert!(Codec::from_tag(99).is_err());
+    }
+}
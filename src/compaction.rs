@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::segment::SegmentStore;
+
+/// How often the background task checks whether any sealed segment has
+/// crossed the dead-record ratio that triggers automatic compaction.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Once a sealed segment is at least this fraction dead records, it is
+/// compacted automatically instead of waiting for a manual `DB::compact()`.
+pub const DEAD_RATIO_THRESHOLD: f64 = 0.5;
+
+/// Runs compaction on a timer in the background, until asked to stop.
+pub struct Compactor {
+    handle: JoinHandle<()>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Compactor {
+    pub fn spawn(store: Arc<SegmentStore>, check_interval: Duration, dead_ratio_threshold: f64) -> Self {
+        let (shutdown, mut shutdown_rx) = watch::channel(false);
+
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(check_interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        store.compact_if_dead_ratio_exceeds(dead_ratio_threshold).await;
+                    }
+                    _ = shutdown_rx.changed() => {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Compactor { handle, shutdown }
+    }
+
+    /// Signals the background loop to stop and waits for it to reach a
+    /// clean stopping point (finishing or aborting any in-flight
+    /// compaction) before returning, so no segment is left half written.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.handle.await;
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+use std::io;
+
+/// Errors surfaced by the storage layer. `Io` covers genuine filesystem
+/// failures; `Corrupt` is kept distinct so callers (and compaction /
+/// startup replay) can tell "the disk broke" apart from "this record's
+/// checksum doesn't match what was written", which calls for skipping the
+/// record rather than retrying it.
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Corrupt { key: String },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "{e}"),
+            StoreError::Corrupt { key } => {
+                write!(f, "checksum mismatch for key {key:?}: record is corrupt")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+pub type StoreResult<T> = std::result::Result<T, StoreError>;
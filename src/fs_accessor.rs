@@ -0,0 +1,161 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncWrite, ReadBuf};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Gates how many files may be open at once, independently of how many
+/// tasks are running concurrently. Without this, spawning far more tasks
+/// than the process FD limit (e.g. `concurrency_limit = 10_000`) can blow
+/// past `ulimit -n` and fail with EMFILE.
+pub struct FileSystemAccessor {
+    semaphore: Arc<Semaphore>,
+}
+
+impl FileSystemAccessor {
+    pub fn new(max_open_files: usize) -> Self {
+        FileSystemAccessor {
+            semaphore: Arc::new(Semaphore::new(max_open_files)),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    pub async fn open(&self, path: &Path) -> io::Result<ManagedFile> {
+        let permit = self.acquire().await;
+        let file = File::open(path).await?;
+        Ok(ManagedFile {
+            file,
+            _permit: permit,
+        })
+    }
+
+    /// Opens `path` for appending, creating it if necessary, matching how
+    /// the active segment is opened.
+    pub async fn open_append(&self, path: &Path) -> io::Result<ManagedFile> {
+        let permit = self.acquire().await;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(path)
+            .await?;
+        Ok(ManagedFile {
+            file,
+            _permit: permit,
+        })
+    }
+
+    /// Reads `path` fully into memory, gated by the same semaphore as
+    /// `open`/`open_append` so a full-segment read counts against the
+    /// open-file budget like any other open.
+    pub async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut file = self.open(path).await?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).await?;
+        Ok(buf)
+    }
+
+    /// Lists the entries of `dir`, gated by the same semaphore as file
+    /// opens since a directory listing also holds an FD open while it
+    /// runs.
+    pub async fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let _permit = self.acquire().await;
+        let mut paths = Vec::new();
+        if let Ok(mut entries) = fs::read_dir(dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                paths.push(entry.path());
+            }
+        }
+        Ok(paths)
+    }
+
+    async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("file system accessor semaphore should never be closed")
+    }
+}
+
+/// A `File` bundled with the permit that was acquired to open it. The
+/// permit is released back to the `FileSystemAccessor` when this value is
+/// dropped, so the open stays counted for as long as the handle is held.
+pub struct ManagedFile {
+    file: File,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl ManagedFile {
+    pub async fn metadata(&self) -> io::Result<std::fs::Metadata> {
+        self.file.metadata().await
+    }
+}
+
+impl AsyncRead for ManagedFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().file).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ManagedFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().file).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().file).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().file).poll_shutdown(cx)
+    }
+}
+
+impl AsyncSeek for ManagedFile {
+    fn start_seek(self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.get_mut().file).start_seek(position)
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.get_mut().file).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_permit_is_released_when_handle_drops() {
+        let accessor = FileSystemAccessor::new(1);
+        assert_eq!(accessor.available_permits(), 1);
+
+        let dir = std::env::temp_dir().join("simple_kv_fs_accessor");
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join("probe.blob");
+
+        {
+            let _file = accessor.open_append(&path).await.unwrap();
+            assert_eq!(accessor.available_permits(), 0);
+        }
+
+        assert_eq!(accessor.available_permits(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,604 @@
+use std::collections::HashMap;
+use std::io::{self, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+use crate::codec::Codec;
+use crate::error::{StoreError, StoreResult};
+use crate::fs_accessor::{FileSystemAccessor, ManagedFile};
+
+/// Roll over to a new segment file once the active one reaches this size.
+const SEGMENT_SIZE_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+struct IndexEntry {
+    segment_id: u64,
+    offset: u64,
+    /// Length of the stored (possibly compressed) bytes on disk, not the
+    /// decoded value length.
+    len: u32,
+    /// CRC32 of the stored bytes, checked against the record header on
+    /// every read so a torn write or bit-rot surfaces as
+    /// `StoreError::Corrupt` instead of being returned as if valid.
+    crc: u32,
+    /// Which [`Codec`] the stored bytes were written with, so a reader can
+    /// decode a record correctly even after the store's default codec has
+    /// since changed.
+    codec: u8,
+}
+
+struct ActiveSegment {
+    id: u64,
+    file: ManagedFile,
+    size: u64,
+}
+
+/// Append-only value storage backed by a handful of large `segment.N.blob`
+/// files instead of one file per key. Writes are appended to the active
+/// segment; an in-memory index maps each key to the `(segment_id, offset,
+/// len)` of its most recent value so reads are a single positioned read.
+pub struct SegmentStore {
+    dir: PathBuf,
+    index: Mutex<HashMap<String, IndexEntry>>,
+    active: Mutex<ActiveSegment>,
+    /// Final byte size of each segment once it stops being the active one,
+    /// used to work out what fraction of a segment is dead for compaction.
+    segment_sizes: Mutex<HashMap<u64, u64>>,
+    fs: Arc<FileSystemAccessor>,
+    /// Codec applied to values written by `put`. Each record keeps its own
+    /// codec tag, so changing this doesn't require rewriting old records.
+    default_codec: Codec,
+    raw_bytes_written: AtomicU64,
+    stored_bytes_written: AtomicU64,
+}
+
+impl SegmentStore {
+    /// Opens `dir`, replaying every existing segment to rebuild the index
+    /// (later records win) and resuming appends onto the newest segment.
+    /// All file opens are gated through `fs` so concurrent tasks can't
+    /// exhaust the process FD limit.
+    pub async fn open(
+        dir: PathBuf,
+        fs_accessor: Arc<FileSystemAccessor>,
+        default_codec: Codec,
+    ) -> Self {
+        let mut segment_ids: Vec<u64> = fs_accessor
+            .read_dir(&dir)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|path| segment_id_from_path(path))
+            .collect();
+        segment_ids.sort_unstable();
+
+        let mut index = HashMap::new();
+        let mut segment_sizes = HashMap::new();
+        for &id in &segment_ids {
+            let size = replay_segment(&dir, id, &fs_accessor, &mut index).await;
+            segment_sizes.insert(id, size);
+        }
+
+        let active_id = segment_ids.last().copied().unwrap_or(0);
+        segment_sizes.remove(&active_id);
+        let active_path = segment_path(&dir, active_id);
+        let file = fs_accessor
+            .open_append(&active_path)
+            .await
+            .expect("failed to open active segment");
+        let size = file
+            .metadata()
+            .await
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+
+        SegmentStore {
+            dir,
+            index: Mutex::new(index),
+            active: Mutex::new(ActiveSegment {
+                id: active_id,
+                file,
+                size,
+            }),
+            segment_sizes: Mutex::new(segment_sizes),
+            fs: fs_accessor,
+            default_codec,
+            raw_bytes_written: AtomicU64::new(0),
+            stored_bytes_written: AtomicU64::new(0),
+        }
+    }
+
+    /// Keys known to the index, used to prime the Bloom filter on startup.
+    pub async fn keys(&self) -> Vec<String> {
+        self.index.lock().await.keys().cloned().collect()
+    }
+
+    /// Total bytes of decoded values ever passed to `put`, for measuring
+    /// the compression ratio alongside [`SegmentStore::stored_bytes`].
+    pub fn raw_bytes(&self) -> u64 {
+        self.raw_bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Total bytes actually written to disk for those same values, after
+    /// encoding with the configured codec.
+    pub fn stored_bytes(&self) -> u64 {
+        self.stored_bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Appends `val` and installs it in the index. `append_to_active` keeps
+    /// the `active` segment lock held across the index insert, so two
+    /// concurrent `put`s for the same key can't append in one order but
+    /// install into the index in the other, which would leave the index
+    /// pointing at the older record and break last-write-wins.
+    pub async fn put(&self, key: &str, val: &str) -> StoreResult<()> {
+        self.append_to_active(key, val).await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> StoreResult<Option<String>> {
+        let entry = self.index.lock().await.get(key).copied();
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.read_at(key, entry).await.map(Some)
+    }
+
+    /// Encodes `val` with the store's default codec, appends it to the
+    /// active segment and installs the new location in the index before
+    /// releasing the `active` lock, so a concurrent `put` for the same key
+    /// can't append after this one but install into the index before it.
+    async fn append_to_active(&self, key: &str, val: &str) -> StoreResult<IndexEntry> {
+        let val_bytes = val.as_bytes();
+        let stored_bytes = self.default_codec.encode(val_bytes)?;
+
+        self.raw_bytes_written
+            .fetch_add(val_bytes.len() as u64, Ordering::Relaxed);
+        self.stored_bytes_written
+            .fetch_add(stored_bytes.len() as u64, Ordering::Relaxed);
+
+        let codec = self.default_codec.tag();
+        let mut active = self.active.lock().await;
+        let entry = self
+            .write_stored_bytes(&mut active, key, &stored_bytes, codec)
+            .await?;
+        self.index.lock().await.insert(key.to_string(), entry);
+        Ok(entry)
+    }
+
+    /// Appends already-encoded bytes tagged with `codec` to the active
+    /// segment, without touching the index. Used by compaction to carry a
+    /// record's bytes forward unchanged (so compaction never needs to
+    /// decode a value just to re-encode it with the same codec); the index
+    /// update there is a compare-and-swap against the record being
+    /// compacted, not a blind insert, so it's handled by the caller.
+    async fn append_stored_bytes(
+        &self,
+        key: &str,
+        stored_bytes: &[u8],
+        codec: u8,
+    ) -> StoreResult<IndexEntry> {
+        let mut active = self.active.lock().await;
+        self.write_stored_bytes(&mut active, key, stored_bytes, codec)
+            .await
+    }
+
+    /// Writes a record's header and stored bytes to the already-locked
+    /// active segment and returns where it landed, rolling to a new
+    /// segment if this push crossed the size threshold.
+    async fn write_stored_bytes(
+        &self,
+        active: &mut ActiveSegment,
+        key: &str,
+        stored_bytes: &[u8],
+        codec: u8,
+    ) -> StoreResult<IndexEntry> {
+        let key_bytes = key.as_bytes();
+        let crc = crc32fast::hash(stored_bytes);
+
+        let mut header = Vec::with_capacity(13 + key_bytes.len());
+        header.extend_from_slice(&(key_bytes.len() as u32).to_le_bytes());
+        header.extend_from_slice(key_bytes);
+        header.extend_from_slice(&(stored_bytes.len() as u32).to_le_bytes());
+        header.push(codec);
+        header.extend_from_slice(&crc.to_le_bytes());
+
+        active.file.write_all(&header).await?;
+        active.file.write_all(stored_bytes).await?;
+
+        let segment_id = active.id;
+        let value_offset = active.size + header.len() as u64;
+        active.size += (header.len() + stored_bytes.len()) as u64;
+
+        if active.size >= SEGMENT_SIZE_THRESHOLD {
+            self.roll_segment(active).await?;
+        }
+
+        Ok(IndexEntry {
+            segment_id,
+            offset: value_offset,
+            len: stored_bytes.len() as u32,
+            crc,
+            codec,
+        })
+    }
+
+    async fn read_at(&self, key: &str, entry: IndexEntry) -> StoreResult<String> {
+        let mut file = self
+            .fs
+            .open(&segment_path(&self.dir, entry.segment_id))
+            .await?;
+        file.seek(SeekFrom::Start(entry.offset)).await?;
+
+        let mut buf = vec![0u8; entry.len as usize];
+        file.read_exact(&mut buf).await?;
+
+        if crc32fast::hash(&buf) != entry.crc {
+            return Err(StoreError::Corrupt {
+                key: key.to_string(),
+            });
+        }
+
+        let raw = Codec::from_tag(entry.codec)?.decode(&buf)?;
+        String::from_utf8(raw)
+            .map_err(|e| StoreError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    async fn roll_segment(&self, active: &mut ActiveSegment) -> io::Result<()> {
+        self.segment_sizes
+            .lock()
+            .await
+            .insert(active.id, active.size);
+
+        let next_id = active.id + 1;
+        let path = segment_path(&self.dir, next_id);
+        let file = self.fs.open_append(&path).await?;
+        active.id = next_id;
+        active.file = file;
+        active.size = 0;
+        Ok(())
+    }
+
+    /// Ids of every segment that is no longer the active (appendable) one.
+    async fn sealed_segment_ids(&self) -> Vec<u64> {
+        self.segment_sizes.lock().await.keys().copied().collect()
+    }
+
+    /// Fraction of `id`'s bytes that belong to records no longer reachable
+    /// from the index (i.e. the key was since overwritten or the segment
+    /// never existed).
+    async fn dead_ratio(&self, id: u64) -> f64 {
+        let total = match self.segment_sizes.lock().await.get(&id).copied() {
+            Some(total) if total > 0 => total,
+            _ => return 0.0,
+        };
+
+        let live: u64 = self
+            .index
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, entry)| entry.segment_id == id)
+            .map(|(key, entry)| (13 + key.len() + entry.len as usize) as u64)
+            .sum();
+
+        1.0 - (live as f64 / total as f64)
+    }
+
+    /// Rewrites every still-live record out of sealed segments whose dead
+    /// ratio is at or above `threshold`, then removes any segment left
+    /// with nothing live in it.
+    pub async fn compact_if_dead_ratio_exceeds(&self, threshold: f64) {
+        for id in self.sealed_segment_ids().await {
+            if self.dead_ratio(id).await >= threshold {
+                self.compact_segment(id).await;
+            }
+        }
+    }
+
+    /// Compacts every sealed segment regardless of its dead ratio.
+    pub async fn compact_all(&self) {
+        for id in self.sealed_segment_ids().await {
+            self.compact_segment(id).await;
+        }
+    }
+
+    async fn compact_segment(&self, id: u64) {
+        let bytes = match self.fs.read(&segment_path(&self.dir, id)).await {
+            Ok(bytes) => bytes,
+            Err(_) => return,
+        };
+
+        let mut pos = 0usize;
+        while pos + 4 <= bytes.len() {
+            let key_len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            if pos + key_len > bytes.len() {
+                break;
+            }
+            let key = String::from_utf8_lossy(&bytes[pos..pos + key_len]).into_owned();
+            pos += key_len;
+
+            if pos + 9 > bytes.len() {
+                break;
+            }
+            let val_len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            let codec = bytes[pos];
+            pos += 1;
+            let crc = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            if pos + val_len > bytes.len() {
+                break;
+            }
+            let value_offset = pos as u64;
+            let stored_bytes = &bytes[pos..pos + val_len];
+            pos += val_len;
+
+            if crc32fast::hash(stored_bytes) != crc {
+                eprintln!("segment {id}: dropping corrupt record for key {key:?} during compaction");
+                continue;
+            }
+
+            let old_entry = IndexEntry {
+                segment_id: id,
+                offset: value_offset,
+                len: val_len as u32,
+                crc,
+                codec,
+            };
+            let is_live = self.index.lock().await.get(&key) == Some(&old_entry);
+
+            if is_live {
+                // Carried forward byte-for-byte with its original codec tag,
+                // so compaction never has to decode a value just to
+                // re-encode it unchanged.
+                match self.append_stored_bytes(&key, stored_bytes, codec).await {
+                    Ok(new_entry) => {
+                        let mut index = self.index.lock().await;
+                        if index.get(&key) == Some(&old_entry) {
+                            index.insert(key, new_entry);
+                        }
+                    }
+                    Err(e) => {
+                        // Leave the record where it is; it stays live in the
+                        // old segment and will be retried on the next pass.
+                        eprintln!("compaction: failed to rewrite {key}: {e}");
+                    }
+                }
+            }
+        }
+
+        let still_referenced = self
+            .index
+            .lock()
+            .await
+            .values()
+            .any(|entry| entry.segment_id == id);
+
+        if !still_referenced {
+            let _ = fs::remove_file(segment_path(&self.dir, id)).await;
+            self.segment_sizes.lock().await.remove(&id);
+        }
+    }
+}
+
+fn segment_path(dir: &std::path::Path, id: u64) -> PathBuf {
+    dir.join(format!("segment.{id}.blob"))
+}
+
+fn segment_id_from_path(path: &std::path::Path) -> Option<u64> {
+    let name = path.file_name()?.to_str()?;
+    let id = name.strip_prefix("segment.")?.strip_suffix(".blob")?;
+    id.parse().ok()
+}
+
+/// Replays a single segment file from start to end, overwriting earlier
+/// index entries with later ones so the last write for a key wins. Returns
+/// the segment's byte size.
+async fn replay_segment(
+    dir: &std::path::Path,
+    id: u64,
+    fs: &FileSystemAccessor,
+    index: &mut HashMap<String, IndexEntry>,
+) -> u64 {
+    let bytes = match fs.read(&segment_path(dir, id)).await {
+        Ok(bytes) => bytes,
+        Err(_) => return 0,
+    };
+    let size = bytes.len() as u64;
+
+    let mut pos = 0usize;
+    while pos + 4 <= bytes.len() {
+        let key_len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        pos += 4;
+        if pos + key_len > bytes.len() {
+            break;
+        }
+        let key = String::from_utf8_lossy(&bytes[pos..pos + key_len]).into_owned();
+        pos += key_len;
+
+        if pos + 9 > bytes.len() {
+            break;
+        }
+        let val_len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        pos += 4;
+        let codec = bytes[pos];
+        pos += 1;
+        let crc = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
+        pos += 4;
+        if pos + val_len > bytes.len() {
+            break;
+        }
+        let value_offset = pos as u64;
+        let stored_bytes = &bytes[pos..pos + val_len];
+        pos += val_len;
+
+        if crc32fast::hash(stored_bytes) != crc {
+            eprintln!("segment {id}: skipping corrupt record for key {key:?} during replay");
+            continue;
+        }
+
+        index.insert(
+            key,
+            IndexEntry {
+                segment_id: id,
+                offset: value_offset,
+                len: val_len as u32,
+                crc,
+                codec,
+            },
+        );
+    }
+
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_then_get_roundtrips() {
+        let dir = tempdir("segment_put_get");
+        let store = SegmentStore::open(dir.clone(), Arc::new(FileSystemAccessor::new(64)), Codec::None).await;
+
+        store.put("hello", "world").await.unwrap();
+
+        assert_eq!(store.get("hello").await.unwrap(), Some("world".to_string()));
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_returns_latest_value() {
+        let dir = tempdir("segment_overwrite");
+        let store = SegmentStore::open(dir.clone(), Arc::new(FileSystemAccessor::new(64)), Codec::None).await;
+
+        store.put("key", "first").await.unwrap();
+        store.put("key", "second").await.unwrap();
+
+        assert_eq!(store.get("key").await.unwrap(), Some("second".to_string()));
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_index_is_rebuilt_by_replaying_segments() {
+        let dir = tempdir("segment_replay");
+        {
+            let store = SegmentStore::open(dir.clone(), Arc::new(FileSystemAccessor::new(64)), Codec::None).await;
+            store.put("persisted", "value").await.unwrap();
+        }
+
+        let reopened = SegmentStore::open(dir.clone(), Arc::new(FileSystemAccessor::new(64)), Codec::None).await;
+        assert_eq!(
+            reopened.get("persisted").await.unwrap(),
+            Some("value".to_string())
+        );
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_compact_removes_fully_dead_segment() {
+        let dir = tempdir("segment_compact");
+        let store = SegmentStore::open(dir.clone(), Arc::new(FileSystemAccessor::new(64)), Codec::None).await;
+
+        store.put("a", "old").await.unwrap();
+        {
+            let mut active = store.active.lock().await;
+            store.roll_segment(&mut active).await.unwrap();
+        }
+        store.put("a", "new").await.unwrap();
+
+        assert!(store.dead_ratio(0).await >= 1.0);
+
+        store.compact_if_dead_ratio_exceeds(0.0).await;
+
+        assert_eq!(store.get("a").await.unwrap(), Some("new".to_string()));
+        assert!(!segment_path(&dir, 0).exists());
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_value_is_rejected_on_read() {
+        let dir = tempdir("segment_corrupt");
+        let store = SegmentStore::open(dir.clone(), Arc::new(FileSystemAccessor::new(64)), Codec::None).await;
+
+        store.put("a", "hello").await.unwrap();
+
+        // Flip a bit in the value bytes on disk, simulating bit-rot or a
+        // torn write that the in-memory index doesn't know about.
+        let path = segment_path(&dir, 0);
+        let mut bytes = fs::read(&path).await.unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, &bytes).await.unwrap();
+
+        let err = store.get("a").await.unwrap_err();
+        assert!(matches!(err, StoreError::Corrupt { .. }));
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_deflate_codec_round_trips_and_tracks_byte_counters() {
+        let dir = tempdir("segment_codec");
+        let store = SegmentStore::open(
+            dir.clone(),
+            Arc::new(FileSystemAccessor::new(64)),
+            Codec::Deflate,
+        )
+        .await;
+
+        let value = "a".repeat(1000);
+        store.put("compressible", &value).await.unwrap();
+
+        assert_eq!(store.get("compressible").await.unwrap(), Some(value));
+        assert_eq!(store.raw_bytes(), 1000);
+        assert!(store.stored_bytes() < 1000);
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_reopen_after_codec_change_still_reads_old_records() {
+        let dir = tempdir("segment_codec_migrate");
+        {
+            let store = SegmentStore::open(
+                dir.clone(),
+                Arc::new(FileSystemAccessor::new(64)),
+                Codec::None,
+            )
+            .await;
+            store.put("old", "value").await.unwrap();
+        }
+
+        // Reopening with a different default codec must not disturb
+        // records written under the previous one: each keeps its own tag.
+        let store = SegmentStore::open(
+            dir.clone(),
+            Arc::new(FileSystemAccessor::new(64)),
+            Codec::Deflate,
+        )
+        .await;
+        assert_eq!(store.get("old").await.unwrap(), Some("value".to_string()));
+
+        store.put("new", "value").await.unwrap();
+        assert_eq!(store.get("new").await.unwrap(), Some("value".to_string()));
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+
+    fn tempdir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("simple_kv_{name}"));
+        let _ = std::fs::create_dir_all(&dir);
+        dir
+    }
+}